@@ -1,9 +1,11 @@
-use std::io::{self, BufRead, BufWriter};
+use std::io::{self, BufRead};
 use std::env;
 use std::collections::HashMap;
+use std::fmt;
 use std::fs::File;
 use std::path::Path;
 use std::io::Write;
+use std::process::ExitCode;
 
 use lscolors::{LsColors, Style};
 
@@ -43,6 +45,48 @@ where P: AsRef<Path>, {
     Ok(io::BufReader::new(file).lines())
 }
 
+// Decodes a git core.quotePath-style C-quoted path: strips the surrounding
+// double quotes (if present) and unescapes `\t`, `\n`, `\"`, `\\`, and `\NNN`
+// octal byte escapes, collecting consecutive octal escapes into raw bytes
+// before interpreting the result as UTF-8 (lossily, since the octal escapes
+// can split a multi-byte UTF-8 sequence across several `\NNN` runs).
+fn unquote_git_path(s: &str) -> String {
+    if s.len() < 2 || !s.starts_with('"') || !s.ends_with('"') {
+        return s.to_string();
+    }
+
+    let bytes = &s.as_bytes()[1..s.len()-1];
+    let mut out: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 1 < bytes.len() {
+            match bytes[i+1] {
+                b't' => { out.push(b'\t'); i += 2; }
+                b'n' => { out.push(b'\n'); i += 2; }
+                b'"' => { out.push(b'"'); i += 2; }
+                b'\\' => { out.push(b'\\'); i += 2; }
+                b'0'..=b'7' => {
+                    let mut val: u32 = 0;
+                    let mut j = i + 1;
+                    let mut digits = 0;
+                    while j < bytes.len() && digits < 3 && (b'0'..=b'7').contains(&bytes[j]) {
+                        val = val * 8 + (bytes[j] - b'0') as u32;
+                        j += 1;
+                        digits += 1;
+                    }
+                    out.push(val as u8);
+                    i = j;
+                }
+                other => { out.push(other); i += 2; }
+            }
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
 const T_CREATE: &str = "create mode ";
 const T_DELETE: &str = "delete mode ";
 const T_RENAME: &str = "rename ";
@@ -52,28 +96,195 @@ const RARROW: &str = "→";
 const SQUARE: &str = "▪";
 const CIRCLE: &str = "●";
 
-fn main() {
+// Selects the emitter used to report each processed stdin path, mirroring how
+// rustfmt picks between its human/json/checkstyle emitters.
+#[derive(Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Human,
+    Json,
+}
+
+impl OutputFormat {
+    fn parse(s: &str) -> Option<OutputFormat> {
+        match s {
+            "human" => Some(OutputFormat::Human),
+            "json" => Some(OutputFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+// Escapes a string for embedding in a JSON string literal.
+fn json_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+// Emits one JSON object describing a processed stdin path, suppressing all ANSI escapes.
+fn emit_json_entry(
+    handle: &mut dyn Write,
+    path: &str,
+    kind: &str,
+    similarity: Option<&str>,
+    renamed_to: Option<&str>,
+    renamed_from: Option<&str>,
+) -> io::Result<()> {
+    let mut obj = format!("{{\"path\": \"{}\", \"kind\": \"{}\"", json_escape(path), kind);
+    if let Some(similarity) = similarity.and_then(|s| s.trim_end_matches('%').parse::<u32>().ok()) {
+        obj.push_str(&format!(", \"similarity\": {}", similarity));
+    }
+    if let Some(renamed_to) = renamed_to {
+        obj.push_str(&format!(", \"renamed_to\": \"{}\"", json_escape(renamed_to)));
+    }
+    if let Some(renamed_from) = renamed_from {
+        obj.push_str(&format!(", \"renamed_from\": \"{}\"", json_escape(renamed_from)));
+    }
+    obj.push('}');
+    writeln!(handle, "{}", obj)
+}
+
+// Parses a `--remap-path-prefix OLD=NEW` argument value into its pair.
+fn parse_remap_pair(s: &str) -> Option<(String, String)> {
+    let idx = s.find('=')?;
+    Some((s[..idx].to_string(), s[idx+1..].to_string()))
+}
+
+// Rewrites the first matching `OLD` prefix in `path` to its paired `NEW` value.
+// Pairs are tried in the order they were given on the command line.
+fn remap_path(remap_pairs: &[(String, String)], path: &str) -> String {
+    for (old, new) in remap_pairs {
+        if let Some(rest) = path.strip_prefix(old.as_str()) {
+            return format!("{}{}", new, rest);
+        }
+    }
+    path.to_string()
+}
+
+// Writes `contents` to a sibling temp file and renames it over `summary_out`,
+// so preview_git_show.sh/git_show.sh never observe a half-written file if the
+// process is interrupted mid-run or two invocations race.
+fn write_summary_out_atomically(summary_out: &str, contents: &str) -> Result<(), AppError> {
+    let tmp_path = format!("{}.tmp.{}", summary_out, std::process::id());
+
+    let mut tmp_file = match File::create(&tmp_path) {
+        Ok(file) => file,
+        Err(err) => {
+            let _ = std::fs::remove_file(&tmp_path);
+            return Err(AppError::OutputUncreatable(summary_out.to_string(), err));
+        }
+    };
+
+    let write_result = tmp_file.write_all(contents.as_bytes()).and_then(|_| tmp_file.flush());
+    drop(tmp_file);
+
+    if let Err(err) = write_result {
+        let _ = std::fs::remove_file(&tmp_path);
+        return Err(AppError::WriteFailure(err));
+    }
+
+    if let Err(err) = std::fs::rename(&tmp_path, summary_out) {
+        let _ = std::fs::remove_file(&tmp_path);
+        return Err(AppError::WriteFailure(err));
+    }
+
+    Ok(())
+}
+
+#[derive(Debug)]
+enum AppError {
+    Usage(String),
+    SummaryUnreadable(String, io::Error),
+    OutputUncreatable(String, io::Error),
+    WriteFailure(io::Error),
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AppError::Usage(msg) => write!(f, "usage error: {}", msg),
+            AppError::SummaryUnreadable(path, err) => write!(f, "could not read summary file '{}': {}", path, err),
+            AppError::OutputUncreatable(path, err) => write!(f, "could not create output file '{}': {}", path, err),
+            AppError::WriteFailure(err) => write!(f, "failed writing rename-entry file: {}", err),
+        }
+    }
+}
+
+impl AppError {
+    fn exit_code(&self) -> u8 {
+        match self {
+            AppError::Usage(_) => 2,
+            AppError::SummaryUnreadable(..) => 3,
+            AppError::OutputUncreatable(..) => 4,
+            AppError::WriteFailure(_) => 5,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    match try_main() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("apply_git_summary: {}", err);
+            ExitCode::from(err.exit_code())
+        }
+    }
+}
+
+fn try_main() -> Result<(), AppError> {
     let ls_colors = LsColors::from_env().unwrap_or_default();
     let mut stdout = io::stdout();
 
     let summary_in;
     let summary_out;
+    let mut remap_pairs: Vec<(String, String)> = Vec::new();
+    let mut format = OutputFormat::Human;
 
     let args: Vec<_> = env::args().collect();
-    if args.len() == 3 {
-        summary_in = &args[1];
-        summary_out = &args[2];
-    } else {
-        return;
+    let mut positional: Vec<&String> = Vec::new();
+    let mut i = 1;
+    while i < args.len() {
+        if args[i] == "--remap-path-prefix" {
+            if i + 1 >= args.len() {
+                return Err(AppError::Usage("--remap-path-prefix requires a FROM=TO argument".to_string()));
+            }
+            match parse_remap_pair(&args[i+1]) {
+                Some(pair) => remap_pairs.push(pair),
+                None => return Err(AppError::Usage(format!("invalid --remap-path-prefix value '{}', expected FROM=TO", args[i+1]))),
+            }
+            i += 2;
+        } else if args[i] == "--format" {
+            if i + 1 >= args.len() {
+                return Err(AppError::Usage("--format requires a human|json argument".to_string()));
+            }
+            match OutputFormat::parse(&args[i+1]) {
+                Some(parsed) => format = parsed,
+                None => return Err(AppError::Usage(format!("invalid --format value '{}', expected human or json", args[i+1]))),
+            }
+            i += 2;
+        } else {
+            positional.push(&args[i]);
+            i += 1;
+        }
     }
-
-    let file_out;
-    if let Ok(file) = File::create(summary_out) {
-        file_out = file;
+    if positional.len() == 2 {
+        summary_in = positional[0];
+        summary_out = positional[1];
     } else {
-        return;
+        return Err(AppError::Usage(format!("usage: {} [--remap-path-prefix FROM=TO]... [--format human|json] SUMMARY_IN SUMMARY_OUT", args[0])));
     }
-    let mut writer = BufWriter::new(&file_out);
+
+    let mut rename_entries = String::new();
 
     let mut create_map: HashMap<String, String> = HashMap::new();
     let mut delete_map: HashMap<String, String> = HashMap::new();
@@ -81,7 +292,9 @@ fn main() {
     let mut to_map: HashMap<String, String> = HashMap::new();
     let mut percent_map: HashMap<String, String> = HashMap::new();
 
-    if let Ok(lines) = read_lines(summary_in) {
+    {
+        let lines = read_lines(summary_in)
+            .map_err(|err| AppError::SummaryUnreadable(summary_in.to_string(), err))?;
         for line in lines.flatten() {
             let mut ln = line.trim();
             if ln.starts_with(T_CREATE) {
@@ -89,18 +302,18 @@ fn main() {
                 ln = &ln[T_CREATE.len()..];
                 // consume mode numbers
                 if let Some(idx) = ln.find(" ") {
-                    let filename = &ln[idx+1..];
+                    let filename = remap_path(&remap_pairs, &unquote_git_path(&ln[idx+1..]));
                     // println!("CREATE: |{}|", filename);
-                    create_map.insert(filename.to_string(), "true".to_string());
+                    create_map.insert(filename, "true".to_string());
                 }
             } else if ln.starts_with(T_DELETE) {
                 // consume header
                 ln = &ln[T_DELETE.len()..];
                 // consume mode numbers
                 if let Some(idx) = ln.find(" ") {
-                    let filename = &ln[idx+1..];
+                    let filename = remap_path(&remap_pairs, &unquote_git_path(&ln[idx+1..]));
                     // println!("DELETE: |{}|", filename);
-                    delete_map.insert(filename.to_string(), "true".to_string());
+                    delete_map.insert(filename, "true".to_string());
                 }
             } else if ln.starts_with(T_RENAME) {
                 // consume header
@@ -125,14 +338,16 @@ fn main() {
                 let rb = ln.find("}");
                 let sp = ln.find(" => ");
                 if let (Some(lbracket), Some(rbracket), Some(split)) = (lb, rb, sp) {
-                    let prefix = &ln[..lbracket];
-                    let from = &ln[lbracket+1..split];
-                    let to = &ln[split+4..rbracket];
-                    let postfix = &ln[rbracket+1..];
+                    let prefix = unquote_git_path(&ln[..lbracket]);
+                    let from = unquote_git_path(&ln[lbracket+1..split]);
+                    let to = unquote_git_path(&ln[split+4..rbracket]);
+                    let postfix = unquote_git_path(&ln[rbracket+1..]);
                     let mut path_from = format!("{}{}{}", prefix, from, postfix);
                     let mut path_to = format!("{}{}{}", prefix, to, postfix);
                     path_from = path_from.replace("//", "/"); // fix for empty from
                     path_to = path_to.replace("//", "/");     // fix for empty to
+                    path_from = remap_path(&remap_pairs, &path_from);
+                    path_to = remap_path(&remap_pairs, &path_to);
                     // println!("PATH_FROM=|{}|", path_from);
                     // println!("PATH_TO=  |{}|", path_to);
 
@@ -146,11 +361,10 @@ fn main() {
                     // Populate the rename entry file for preview_git_show.sh/git_show.sh
                     // Note that colon(:) is actuqlly allowed characer in linux filesystem,
                     // however I believe "::" is not a common pattern in filename. So use it as delimiter.
-                    let buff = format!("{}::{}::{}\n", path_from, path_to, percent);
-                    let _ = writer.write(buff.as_bytes());
+                    rename_entries.push_str(&format!("{}::{}::{}\n", path_from, path_to, percent));
                 } else if let Some(split) = sp {
-                    let path_from = &ln[..split];
-                    let path_to = &ln[split+4..];
+                    let path_from = remap_path(&remap_pairs, &unquote_git_path(&ln[..split]));
+                    let path_to = remap_path(&remap_pairs, &unquote_git_path(&ln[split+4..]));
                     // println!("FROM=|{}| TO=|{}|", path_from, path_to);
 
                     to_map.insert(path_from.to_string(), path_to.to_string());
@@ -163,48 +377,85 @@ fn main() {
                     // Populate the rename entry file for preview_git_show.sh/git_show.sh
                     // Note that colon(:) is actuqlly allowed characer in linux filesystem,
                     // however I believe "::" is not a common pattern in filename. So use it as delimiter.
-                    let buff = format!("{}::{}::{}\n", path_from, path_to, percent);
-                    let _ = writer.write(buff.as_bytes());
+                    rename_entries.push_str(&format!("{}::{}::{}\n", path_from, path_to, percent));
                 }
             }
         }
-    } else {
-        return;
     }
 
+    write_summary_out_atomically(summary_out, &rename_entries)?;
+
     let stdin = io::stdin();
     for line_data in stdin.lock().lines() {
         if let Ok(line) = line_data {
-            let ln = line.trim();
+            let ln = remap_path(&remap_pairs, line.trim());
+            let ln = ln.as_str();
 
             if let Some(_) = create_map.get(ln) {
-                // Green for create
-                write!(stdout, "\x1b[32m{}\x1b[0m ", CIRCLE).unwrap();
-                print_lscolor_path(&mut stdout, &ls_colors, &ln).unwrap();
-                writeln!(stdout).unwrap();
+                match format {
+                    OutputFormat::Human => {
+                        // Green for create
+                        write!(stdout, "\x1b[32m{}\x1b[0m ", CIRCLE).unwrap();
+                        print_lscolor_path(&mut stdout, &ls_colors, &ln).unwrap();
+                        writeln!(stdout).unwrap();
+                    }
+                    OutputFormat::Json => {
+                        emit_json_entry(&mut stdout, ln, "create", None, None, None).unwrap();
+                    }
+                }
             } else if let Some(_) = delete_map.get(ln) {
-                // Red for removal
-                write!(stdout, "\x1b[31m{}\x1b[0m ", CIRCLE).unwrap();
-                print_lscolor_path(&mut stdout, &ls_colors, &ln).unwrap();
-                writeln!(stdout).unwrap();
-            } else if let (Some(_), Some(percent)) = (to_map.get(ln), percent_map.get(ln)) {
-                // Red for renamed delete. yellow for percent
-                write!(stdout, "\x1b[31m{}\x1b[0m ", LARROW).unwrap();
-                print_lscolor_path(&mut stdout, &ls_colors, &ln).unwrap();
-                write!(stdout, "\t\t\x1b[33m({})\x1b[0m", percent).unwrap();
-                writeln!(stdout).unwrap();
-            } else if let (Some(_), Some(percent)) = (from_map.get(ln), percent_map.get(ln)) {
-                // Green for renamed create. yellow for percent
-                write!(stdout, "\x1b[32m{}\x1b[0m ", RARROW).unwrap();
-                print_lscolor_path(&mut stdout, &ls_colors, &ln).unwrap();
-                write!(stdout, "\t\t\x1b[33m({})\x1b[0m", percent).unwrap();
-                writeln!(stdout).unwrap();
+                match format {
+                    OutputFormat::Human => {
+                        // Red for removal
+                        write!(stdout, "\x1b[31m{}\x1b[0m ", CIRCLE).unwrap();
+                        print_lscolor_path(&mut stdout, &ls_colors, &ln).unwrap();
+                        writeln!(stdout).unwrap();
+                    }
+                    OutputFormat::Json => {
+                        emit_json_entry(&mut stdout, ln, "delete", None, None, None).unwrap();
+                    }
+                }
+            } else if let (Some(renamed_to), Some(percent)) = (to_map.get(ln), percent_map.get(ln)) {
+                match format {
+                    OutputFormat::Human => {
+                        // Red for renamed delete. yellow for percent
+                        write!(stdout, "\x1b[31m{}\x1b[0m ", LARROW).unwrap();
+                        print_lscolor_path(&mut stdout, &ls_colors, &ln).unwrap();
+                        write!(stdout, "\t\t\x1b[33m({})\x1b[0m", percent).unwrap();
+                        writeln!(stdout).unwrap();
+                    }
+                    OutputFormat::Json => {
+                        emit_json_entry(&mut stdout, ln, "rename_from", Some(percent), Some(renamed_to), None).unwrap();
+                    }
+                }
+            } else if let (Some(renamed_from), Some(percent)) = (from_map.get(ln), percent_map.get(ln)) {
+                match format {
+                    OutputFormat::Human => {
+                        // Green for renamed create. yellow for percent
+                        write!(stdout, "\x1b[32m{}\x1b[0m ", RARROW).unwrap();
+                        print_lscolor_path(&mut stdout, &ls_colors, &ln).unwrap();
+                        write!(stdout, "\t\t\x1b[33m({})\x1b[0m", percent).unwrap();
+                        writeln!(stdout).unwrap();
+                    }
+                    OutputFormat::Json => {
+                        emit_json_entry(&mut stdout, ln, "rename_to", Some(percent), None, Some(renamed_from)).unwrap();
+                    }
+                }
             } else {
-                // Blue for normal
-                write!(stdout, "\x1b[34m{}\x1b[0m ", SQUARE).unwrap();
-                print_lscolor_path(&mut stdout, &ls_colors, &ln).unwrap();
-                writeln!(stdout).unwrap();
+                match format {
+                    OutputFormat::Human => {
+                        // Blue for normal
+                        write!(stdout, "\x1b[34m{}\x1b[0m ", SQUARE).unwrap();
+                        print_lscolor_path(&mut stdout, &ls_colors, &ln).unwrap();
+                        writeln!(stdout).unwrap();
+                    }
+                    OutputFormat::Json => {
+                        emit_json_entry(&mut stdout, ln, "modify", None, None, None).unwrap();
+                    }
+                }
             }
         }
     }
+
+    Ok(())
 }